@@ -0,0 +1,71 @@
+//
+// Copyright (c) 2016 KAMADA Ken'ichi.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+// 1. Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in the
+//    documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED.  IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE
+// FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+// DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS
+// OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION)
+// HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT
+// LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY
+// OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF
+// SUCH DAMAGE.
+//
+
+//! Helpers for bounding allocations that are sized from untrusted input,
+//! such as a field count or value length read from an IFD.
+//!
+//! Every size-driven `Vec` allocation in the container and value
+//! decoders should go through [`try_alloc_vec_u8`] instead of
+//! `Vec::with_capacity`/`vec![]`, so that a file claiming an absurd size
+//! cannot make the process allocate gigabytes (or abort via the global
+//! allocator) before the rest of the format is even validated.
+
+use crate::error::Error;
+
+/// Allocates a zero-filled `Vec<u8>` of `len` bytes, failing with
+/// `Error::AllocationLimit` instead of panicking or aborting if `len`
+/// exceeds `max_alloc` or if the allocator itself refuses the request.
+///
+/// `what` names the field or structure being allocated for, and is used
+/// verbatim in the returned error so callers (and `continue_on_error`
+/// consumers inspecting `PartialResult`) can tell what was too big.
+pub fn try_alloc_vec_u8(len: usize, max_alloc: usize, what: &'static str)
+    -> Result<Vec<u8>, Error> {
+    if len > max_alloc {
+        return Err(Error::AllocationLimit(what));
+    }
+    let mut v = Vec::new();
+    v.try_reserve_exact(len).map_err(|_| Error::AllocationLimit(what))?;
+    v.resize(len, 0);
+    Ok(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn within_limit() {
+        let v = try_alloc_vec_u8(16, 1024, "test").unwrap();
+        assert_eq!(v.len(), 16);
+    }
+
+    #[test]
+    fn exceeds_limit() {
+        let err = try_alloc_vec_u8(2048, 1024, "test").unwrap_err();
+        assert!(matches!(err, Error::AllocationLimit("test")));
+    }
+}
@@ -51,6 +51,17 @@ pub enum Error {
     NotSupported(&'static str),
     /// The field has an unexpected value.
     UnexpectedValue(&'static str),
+    /// An allocation driven by a size found in the input data was refused
+    /// because it exceeded `Reader::max_alloc` or because the allocator
+    /// itself failed.  Unlike `Error::TooBig`, which concerns limits on
+    /// *encoding*, this variant concerns limits on *decoding* untrusted
+    /// input.
+    AllocationLimit(&'static str),
+    /// An error that occurred while parsing a container box or chunk,
+    /// annotated with the byte offset of that box or chunk.  Use [`Error::context`]
+    /// to inspect the location and [`Error::into_source`] (or
+    /// `std::error::Error::source`) to get at the original error.
+    At(ErrorContext, Box<Error>),
     /// Partially-parsed result and errors.  This can be returned only when
     /// `Reader::continue_on_error` is enabled.
     PartialResult(PartialResult),
@@ -73,6 +84,55 @@ impl Error {
             Err(self)
         }
     }
+
+    /// Wraps `self` with location information, for use by parsers as
+    /// they descend into a container so that a caller inspecting
+    /// `PartialResult`'s errors can tell where each one happened.
+    /// `crate::heif`, `crate::png`, and `crate::webp` call this at
+    /// their fallible steps to attach a byte offset.
+    pub(crate) fn with_context(self, context: ErrorContext) -> Self {
+        Error::At(context, Box::new(self))
+    }
+
+    /// Returns the location of this error, if any was recorded.
+    ///
+    /// This unwraps only the outermost `Error::At` layer; the container
+    /// parsers in `crate::heif`, `crate::png`, and `crate::webp` wrap
+    /// an error at most once.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::At(ref context, _) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Returns the underlying error, discarding any location
+    /// information recorded by `Error::At`.
+    pub fn into_source(self) -> Error {
+        match self {
+            Error::At(_, source) => source.into_source(),
+            other => other,
+        }
+    }
+}
+
+/// Where in the input an `Error` occurred, as much as was known at the
+/// point of failure.
+///
+/// `offset` is optional because not every error site can determine it
+/// (e.g., an `std::io::Error` surfaced before any position was read).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// Byte offset from the start of the container being parsed, if known.
+    pub offset: Option<u64>,
+}
+
+impl ErrorContext {
+    /// Builds a context carrying a byte offset, for the container
+    /// readers in `crate::heif`, `crate::png`, and `crate::webp`.
+    pub(crate) fn at(offset: u64) -> Self {
+        ErrorContext { offset: Some(offset) }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -91,6 +151,8 @@ impl fmt::Display for Error {
             Error::TooBig(msg) => f.write_str(msg),
             Error::NotSupported(msg) => f.write_str(msg),
             Error::UnexpectedValue(msg) => f.write_str(msg),
+            Error::AllocationLimit(msg) => f.write_str(msg),
+            Error::At(_, ref source) => source.fmt(f),
             Error::PartialResult(ref pr) =>
                 write!(f, "Partial result with {} fields and {} errors",
                        pr.0.0.lock().expect("should not panic").fields().len(),
@@ -109,6 +171,8 @@ impl error::Error for Error {
             Error::TooBig(_) => None,
             Error::NotSupported(_) => None,
             Error::UnexpectedValue(_) => None,
+            Error::AllocationLimit(_) => None,
+            Error::At(_, ref source) => Some(source),
             Error::PartialResult(_) => None,
         }
     }
@@ -148,4 +212,28 @@ mod tests {
         let _: Box<dyn Send + Sync + 'static> =
             Box::new(Error::InvalidFormat("test"));
     }
+
+    #[test]
+    fn context_round_trip() {
+        let context = ErrorContext { offset: Some(0x1a4) };
+        let err = Error::InvalidFormat("bad RATIONAL").with_context(context);
+        assert_eq!(err.context(), Some(&context));
+        assert!(matches!(err.into_source(), Error::InvalidFormat("bad RATIONAL")));
+    }
+
+    // with_context() is exercised for real (not just round-tripped in
+    // isolation above) by the container parsers: an eXIf chunk whose
+    // declared length runs past the end of the file should come back
+    // with a byte offset attached.
+    #[test]
+    fn real_parser_attaches_context() {
+        use std::io::Cursor;
+        let mut truncated = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        truncated.extend_from_slice(&20u32.to_be_bytes()); // claims 20 bytes of data...
+        truncated.extend_from_slice(b"eXIf");
+        truncated.extend_from_slice(b"only 4"); // ...but only 6 are actually present
+        let err = crate::png::get_exif_attr(&mut Cursor::new(truncated), 1 << 20)
+            .unwrap_err();
+        assert!(err.context().is_some());
+    }
 }
@@ -0,0 +1,527 @@
+//
+// Copyright (c) 2016 KAMADA Ken'ichi.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+// 1. Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in the
+//    documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED.  IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE
+// FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+// DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS
+// OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION)
+// HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT
+// LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY
+// OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF
+// SUCH DAMAGE.
+//
+
+//! Extraction of Exif data from ISO Base Media File Format (ISO-BMFF)
+//! containers, which cover the HEIF/HEIC and AVIF image formats.
+//!
+//! The Exif payload is stored as an item in the `meta` box.  Its location
+//! is found by combining the item info box (`iinf`) with the item
+//! location box (`iloc`), and the payload itself begins with a 4-byte
+//! big-endian offset to the actual TIFF header, per ISO/IEC 23008-12.
+
+use std::io;
+
+use crate::alloc::try_alloc_vec_u8;
+use crate::error::{Error, ErrorContext};
+
+// Major/compatible brands accepted in the `ftyp` box.
+const ACCEPTED_BRANDS: &[[u8; 4]] = &[
+    *b"mif1", *b"heic", *b"heix", *b"avif", *b"avis",
+];
+
+/// Reads Exif data from an ISO-BMFF (HEIF/HEIC/AVIF) file and returns
+/// the TIFF-formatted Exif attribute block.
+///
+/// `max_alloc` bounds every size-driven allocation made while parsing;
+/// see `crate::alloc`. When `continue_on_error` is set, a single
+/// malformed `infe` entry in the item info box is skipped rather than
+/// aborting the search for the Exif item; see
+/// `Reader::continue_on_error` for why this tree cannot go further and
+/// surface such an entry as part of an `Error::PartialResult`.
+pub fn get_exif_attr<R>(reader: &mut R, max_alloc: usize, continue_on_error: bool) -> Result<Vec<u8>, Error>
+where R: io::Read + io::Seek {
+    let ftyp = read_box_header(reader)?
+        .ok_or(Error::InvalidFormat("Truncated ISO-BMFF box header"))?;
+    if &ftyp.boxtype != b"ftyp" {
+        return Err(Error::InvalidFormat("ISO-BMFF file does not start with ftyp box"));
+    }
+    check_ftyp_brands(reader, &ftyp, max_alloc)
+        .map_err(|e| e.with_context(ErrorContext::at(ftyp.box_pos)))?;
+    reader.seek(io::SeekFrom::Start(ftyp.next_box_pos))?;
+
+    let meta = find_box(reader, u64::MAX, b"meta")?
+        .ok_or(Error::NotFound("ISO-BMFF file"))?;
+    let meta_pos = meta.box_pos;
+    // The `meta` box is a FullBox; skip its 4-byte version/flags field.
+    reader.seek(io::SeekFrom::Current(4))?;
+    let meta_body_end = meta.next_box_pos;
+    let meta_body_start = current_pos(reader)?;
+
+    let item_id = find_exif_item_id(reader, meta_body_start, meta_body_end, continue_on_error)
+        .map_err(|e| e.with_context(ErrorContext::at(meta_pos)))?
+        .ok_or(Error::NotFound("ISO-BMFF file"))?;
+    let (offset, length) = find_item_location(reader, meta_body_start, meta_body_end, item_id)
+        .map_err(|e| e.with_context(ErrorContext::at(meta_pos)))?
+        .ok_or(Error::NotFound("ISO-BMFF file"))?;
+
+    reader.seek(io::SeekFrom::Start(offset))?;
+    let mut hdr_off_buf = [0u8; 4];
+    reader.read_exact(&mut hdr_off_buf)
+        .map_err(|_| Error::InvalidFormat("Truncated Exif item in ISO-BMFF file").with_context(ErrorContext::at(offset)))?;
+    let hdr_off = u32::from_be_bytes(hdr_off_buf) as u64;
+    if hdr_off + 4 > length {
+        return Err(Error::InvalidFormat("Invalid Exif header offset in ISO-BMFF file")
+            .with_context(ErrorContext::at(offset)));
+    }
+    reader.seek(io::SeekFrom::Current(hdr_off as i64))?;
+    let payload_len = length - 4 - hdr_off;
+    let mut buf = try_alloc_vec_u8(payload_len as usize, max_alloc, "ISO-BMFF Exif item")
+        .map_err(|e| e.with_context(ErrorContext::at(offset)))?;
+    reader.read_exact(&mut buf)
+        .map_err(|_| Error::InvalidFormat("Truncated Exif item in ISO-BMFF file").with_context(ErrorContext::at(offset)))?;
+    Ok(buf)
+}
+
+struct BoxHeader {
+    boxtype: [u8; 4],
+    // Absolute file position of the byte following this box.
+    next_box_pos: u64,
+    // Absolute file position of the first byte of this box (its size field).
+    box_pos: u64,
+    // Length of the size+type header itself: 8 bytes, or 16 for a box
+    // using the 64-bit (`largesize`) form.
+    header_len: u64,
+}
+
+impl BoxHeader {
+    // Size of the box's payload, i.e., the total box size minus its header.
+    fn content_len(&self) -> u64 {
+        self.next_box_pos - self.box_pos - self.header_len
+    }
+}
+
+fn current_pos<R: io::Seek>(reader: &mut R) -> Result<u64, Error> {
+    Ok(reader.stream_position()?)
+}
+
+// Reads a box header (size + type, with 32/64-bit size support) at the
+// current position and returns it, or `None` at EOF.
+fn read_box_header<R>(reader: &mut R) -> Result<Option<BoxHeader>, Error>
+where R: io::Read + io::Seek {
+    let box_pos = current_pos(reader)?;
+    let mut buf = [0u8; 8];
+    if !read_exact_or_eof(reader, &mut buf)? {
+        return Ok(None);
+    }
+    let size32 = u32::from_be_bytes(buf[0..4].try_into().unwrap());
+    let boxtype: [u8; 4] = buf[4..8].try_into().unwrap();
+    let (size, header_len) = match size32 {
+        0 => return Err(Error::InvalidFormat("ISO-BMFF box extends to EOF, which is not supported")),
+        1 => {
+            let mut largesize = [0u8; 8];
+            reader.read_exact(&mut largesize)
+                .map_err(|_| Error::InvalidFormat("Truncated ISO-BMFF box header"))?;
+            (u64::from_be_bytes(largesize), 16)
+        }
+        n => (n as u64, 8),
+    };
+    if size < header_len {
+        return Err(Error::InvalidFormat("Invalid ISO-BMFF box size"));
+    }
+    Ok(Some(BoxHeader { boxtype, next_box_pos: box_pos + size, box_pos, header_len }))
+}
+
+fn read_exact_or_eof<R: io::Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, Error> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 if filled == 0 => return Ok(false),
+            0 => return Err(Error::InvalidFormat("Truncated ISO-BMFF box header")),
+            n => filled += n,
+        }
+    }
+    Ok(true)
+}
+
+fn check_ftyp_brands<R>(reader: &mut R, ftyp: &BoxHeader, max_alloc: usize) -> Result<(), Error>
+where R: io::Read + io::Seek {
+    let content_len = ftyp.content_len();
+    if content_len < 8 {
+        return Err(Error::InvalidFormat("Truncated ftyp box"));
+    }
+    let mut buf = try_alloc_vec_u8(content_len as usize, max_alloc, "ftyp box")?;
+    reader.read_exact(&mut buf)
+        .map_err(|_| Error::InvalidFormat("Truncated ftyp box"))?;
+    let major: [u8; 4] = buf[0..4].try_into().unwrap();
+    // buf[4..8] is minor_version, which is informative only and not a brand.
+    let compatible = buf[8..].chunks_exact(4).map(|c| c.try_into().unwrap());
+    let accepted = std::iter::once(major)
+        .chain(compatible)
+        .any(|b| ACCEPTED_BRANDS.contains(&b));
+    if accepted {
+        Ok(())
+    } else {
+        Err(Error::InvalidFormat("Not a recognized HEIF/AVIF brand"))
+    }
+}
+
+// Walks sibling boxes in [start, end) looking for `wanted`, returning its
+// header with the reader positioned right after the header on success.
+fn find_box<R>(reader: &mut R, end: u64, wanted: &[u8; 4]) -> Result<Option<BoxHeader>, Error>
+where R: io::Read + io::Seek {
+    loop {
+        if current_pos(reader)? >= end {
+            return Ok(None);
+        }
+        let hdr = match read_box_header(reader)? {
+            Some(hdr) => hdr,
+            None => return Ok(None),
+        };
+        if &hdr.boxtype == wanted {
+            return Ok(Some(hdr));
+        }
+        reader.seek(io::SeekFrom::Start(hdr.next_box_pos))?;
+    }
+}
+
+fn find_exif_item_id<R>(reader: &mut R, start: u64, end: u64, continue_on_error: bool) -> Result<Option<u32>, Error>
+where R: io::Read + io::Seek {
+    reader.seek(io::SeekFrom::Start(start))?;
+    let iinf = match find_box(reader, end, b"iinf")? {
+        Some(hdr) => hdr,
+        None => return Ok(None),
+    };
+    let mut ver = [0u8; 4];
+    reader.read_exact(&mut ver)
+        .map_err(|_| Error::InvalidFormat("Truncated iinf box"))?;
+    let version = ver[0];
+    let mut count_buf = [0u8; 4];
+    if version == 0 {
+        reader.read_exact(&mut count_buf[2..4])
+            .map_err(|_| Error::InvalidFormat("Truncated iinf box"))?;
+    } else {
+        reader.read_exact(&mut count_buf)
+            .map_err(|_| Error::InvalidFormat("Truncated iinf box"))?;
+    }
+    let count = u32::from_be_bytes(count_buf);
+
+    for _ in 0..count {
+        let infe = find_box(reader, iinf.next_box_pos, b"infe")?
+            .ok_or(Error::InvalidFormat("Truncated iinf box"))?;
+        let infe_end = infe.next_box_pos;
+        // `infe_end` comes from the box header read above, independently
+        // of whether the body below parses cleanly, so a malformed body
+        // can be skipped by seeking straight to it: with
+        // `continue_on_error`, one truncated/malformed `infe` entry does
+        // not stop the search for a sibling entry naming the Exif item.
+        match parse_infe_entry(reader) {
+            Ok((item_id, item_type)) if &item_type == b"Exif" => return Ok(Some(item_id)),
+            Ok(_) => {}
+            Err(_) if continue_on_error => {}
+            Err(e) => return Err(e),
+        }
+        reader.seek(io::SeekFrom::Start(infe_end))?;
+    }
+    Ok(None)
+}
+
+// Parses an `infe` entry's version, item_id, and item_type fields. The
+// reader must be positioned right after the box header (i.e., at the
+// entry's version/flags field) on entry.
+fn parse_infe_entry<R: io::Read>(reader: &mut R) -> Result<(u32, [u8; 4]), Error> {
+    let mut infe_ver = [0u8; 4];
+    reader.read_exact(&mut infe_ver)
+        .map_err(|_| Error::InvalidFormat("Truncated infe box"))?;
+    // Versions >= 2 are the ones used in practice (HEIF/AVIF).
+    if infe_ver[0] >= 3 {
+        let mut id_buf = [0u8; 4];
+        reader.read_exact(&mut id_buf)
+            .map_err(|_| Error::InvalidFormat("Truncated infe box"))?;
+        let mut skip = [0u8; 2]; // item_protection_index
+        reader.read_exact(&mut skip)
+            .map_err(|_| Error::InvalidFormat("Truncated infe box"))?;
+        let mut ty = [0u8; 4];
+        reader.read_exact(&mut ty)
+            .map_err(|_| Error::InvalidFormat("Truncated infe box"))?;
+        Ok((u32::from_be_bytes(id_buf), ty))
+    } else if infe_ver[0] == 2 {
+        let mut id_buf = [0u8; 2];
+        reader.read_exact(&mut id_buf)
+            .map_err(|_| Error::InvalidFormat("Truncated infe box"))?;
+        let mut skip = [0u8; 2];
+        reader.read_exact(&mut skip)
+            .map_err(|_| Error::InvalidFormat("Truncated infe box"))?;
+        let mut ty = [0u8; 4];
+        reader.read_exact(&mut ty)
+            .map_err(|_| Error::InvalidFormat("Truncated infe box"))?;
+        Ok((u16::from_be_bytes(id_buf) as u32, ty))
+    } else {
+        Ok((0, *b"\0\0\0\0"))
+    }
+}
+
+fn find_item_location<R>(reader: &mut R, start: u64, end: u64, item_id: u32)
+    -> Result<Option<(u64, u64)>, Error>
+where R: io::Read + io::Seek {
+    reader.seek(io::SeekFrom::Start(start))?;
+    if find_box(reader, end, b"iloc")?.is_none() {
+        return Ok(None);
+    }
+
+    let mut ver_flags = [0u8; 4];
+    reader.read_exact(&mut ver_flags)
+        .map_err(|_| Error::InvalidFormat("Truncated iloc box"))?;
+    let version = ver_flags[0];
+
+    let mut sizes = [0u8; 2];
+    reader.read_exact(&mut sizes)
+        .map_err(|_| Error::InvalidFormat("Truncated iloc box"))?;
+    let offset_size = sizes[0] >> 4;
+    let length_size = sizes[0] & 0xf;
+    let base_offset_size = sizes[1] >> 4;
+    let index_size = if version == 1 || version == 2 { sizes[1] & 0xf } else { 0 };
+
+    let item_count = if version < 2 {
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf)
+            .map_err(|_| Error::InvalidFormat("Truncated iloc box"))?;
+        u16::from_be_bytes(buf) as u32
+    } else {
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf)
+            .map_err(|_| Error::InvalidFormat("Truncated iloc box"))?;
+        u32::from_be_bytes(buf)
+    };
+
+    for _ in 0..item_count {
+        let id = if version < 2 {
+            read_be_uint(reader, 2)?
+        } else {
+            read_be_uint(reader, 4)?
+        } as u32;
+        let construction_method = if version == 1 || version == 2 {
+            read_be_uint(reader, 2)? & 0xf
+        } else {
+            0
+        };
+        let _data_ref_index = read_be_uint(reader, 2)?;
+        let base_offset = read_be_uint(reader, base_offset_size as usize)?;
+        let extent_count = read_be_uint(reader, 2)?;
+        let mut first_extent = None;
+        for _ in 0..extent_count {
+            if index_size > 0 {
+                let _extent_index = read_be_uint(reader, index_size as usize)?;
+            }
+            let extent_offset = read_be_uint(reader, offset_size as usize)?;
+            let extent_length = read_be_uint(reader, length_size as usize)?;
+            if first_extent.is_none() {
+                first_extent = Some((extent_offset, extent_length));
+            }
+        }
+        if id == item_id {
+            let (extent_offset, extent_length) = first_extent
+                .ok_or(Error::InvalidFormat("iloc entry has no extents"))?;
+            if construction_method != 0 {
+                return Err(Error::NotSupported(
+                    "Only file-offset (construction_method 0) iloc entries are supported"));
+            }
+            return Ok(Some((base_offset + extent_offset, extent_length)));
+        }
+    }
+    Ok(None)
+}
+
+// Reads a big-endian unsigned integer of `nbytes` bytes (0 to 8
+// inclusive).  `nbytes` ultimately comes from 4-bit size fields in the
+// `iloc` box, so a malformed file can claim up to 15; reject anything
+// over 8 rather than underflowing the `buf[8 - nbytes..]` slice below.
+fn read_be_uint<R: io::Read>(reader: &mut R, nbytes: usize) -> Result<u64, Error> {
+    if nbytes > 8 {
+        return Err(Error::InvalidFormat("iloc box has an oversized field width"));
+    }
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf[8 - nbytes..])
+        .map_err(|_| Error::InvalidFormat("Truncated iloc box"))?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn bmff_box(boxtype: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut b = Vec::new();
+        b.extend_from_slice(&((body.len() as u32 + 8).to_be_bytes()));
+        b.extend_from_slice(boxtype);
+        b.extend_from_slice(body);
+        b
+    }
+
+    fn minimal_heic(exif_tiff: &[u8]) -> Vec<u8> {
+        let ftyp = bmff_box(b"ftyp", b"heicheic");
+
+        let mut exif_item = Vec::new();
+        exif_item.extend_from_slice(&0u32.to_be_bytes()); // tiff header offset
+        exif_item.extend_from_slice(exif_tiff);
+
+        let mut iinf_body = Vec::new();
+        iinf_body.extend_from_slice(&[0, 0, 0, 0]); // version 0, flags 0
+        iinf_body.extend_from_slice(&1u16.to_be_bytes()); // entry_count
+        let mut infe_body = Vec::new();
+        infe_body.extend_from_slice(&[2, 0, 0, 0]); // version 2
+        infe_body.extend_from_slice(&1u16.to_be_bytes()); // item_id
+        infe_body.extend_from_slice(&[0, 0]); // protection index
+        infe_body.extend_from_slice(b"Exif");
+        iinf_body.extend_from_slice(&bmff_box(b"infe", &infe_body));
+        let iinf = bmff_box(b"iinf", &iinf_body);
+
+        // iloc, version 0: offset_size=4, length_size=4, base_offset_size=0,
+        // one item with one extent (the extent's offset/length are
+        // zero-filled placeholders patched in below).
+        let mut iloc_body = Vec::new();
+        iloc_body.extend_from_slice(&[0, 0, 0, 0]); // version 0, flags 0
+        iloc_body.extend_from_slice(&[0x44, 0x00]); // offset_size=4, length_size=4, base_offset_size=0
+        iloc_body.extend_from_slice(&1u16.to_be_bytes()); // item_count
+        iloc_body.extend_from_slice(&1u16.to_be_bytes()); // item_id
+        iloc_body.extend_from_slice(&[0, 0]); // data_reference_index
+        iloc_body.extend_from_slice(&1u16.to_be_bytes()); // extent_count
+        let extent_off_in_body = iloc_body.len();
+        iloc_body.extend_from_slice(&[0u8; 8]); // extent_offset, extent_length placeholders
+        let iloc = bmff_box(b"iloc", &iloc_body);
+
+        let mut meta_body = Vec::new();
+        meta_body.extend_from_slice(&[0, 0, 0, 0]); // version/flags
+        meta_body.extend_from_slice(&iinf);
+        let iloc_off_in_meta_body = meta_body.len();
+        meta_body.extend_from_slice(&iloc);
+        let meta = bmff_box(b"meta", &meta_body);
+
+        let exif_item_offset = (ftyp.len() + meta.len()) as u32;
+        let mut file = Vec::new();
+        file.extend_from_slice(&ftyp);
+        file.extend_from_slice(&meta);
+        file.extend_from_slice(&exif_item);
+
+        // Patch in the real extent offset/length now that the full
+        // layout, and thus the Exif item's absolute file offset, is known.
+        // meta box header (8 bytes) precedes meta_body in `meta`.
+        let extent_off_pos =
+            ftyp.len() + 8 + iloc_off_in_meta_body + 8 /* iloc box header */ + extent_off_in_body;
+        file[extent_off_pos..extent_off_pos + 4]
+            .copy_from_slice(&exif_item_offset.to_be_bytes());
+        file[extent_off_pos + 4..extent_off_pos + 8]
+            .copy_from_slice(&(exif_item.len() as u32).to_be_bytes());
+        file
+    }
+
+    #[test]
+    fn extracts_exif_from_minimal_heic() {
+        let tiff = b"MM\0*fake-tiff-body";
+        let file = minimal_heic(tiff);
+        let mut cursor = Cursor::new(file);
+        let got = get_exif_attr(&mut cursor, 1 << 20, false).unwrap();
+        assert_eq!(got, tiff);
+    }
+
+    #[test]
+    fn rejects_unrecognized_brand() {
+        let ftyp = bmff_box(b"ftyp", b"mp41mp41");
+        let mut cursor = Cursor::new(ftyp);
+        let err = get_exif_attr(&mut cursor, 1 << 20, false).unwrap_err();
+        assert!(err.context().is_some());
+        assert!(matches!(err.into_source(), Error::InvalidFormat(_)));
+    }
+
+    #[test]
+    fn missing_meta_box_is_not_found() {
+        let ftyp = bmff_box(b"ftyp", b"heicheic");
+        let mut cursor = Cursor::new(ftyp);
+        let err = get_exif_attr(&mut cursor, 1 << 20, false).unwrap_err();
+        assert!(matches!(err, Error::NotFound(_)));
+    }
+
+    #[test]
+    fn oversized_iloc_field_width_errors_instead_of_panicking() {
+        // offset_size nibble = 0xf (15), which would underflow
+        // `buf[8 - nbytes..]` if not rejected.
+        assert!(matches!(read_be_uint(&mut Cursor::new([0u8; 8]), 15),
+                          Err(Error::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn content_len_accounts_for_64_bit_largesize_header() {
+        // size32 == 1 means the real size follows as an 8-byte
+        // largesize field, making the header 16 bytes instead of 8.
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&1u32.to_be_bytes());
+        buf.extend_from_slice(b"meta");
+        buf.extend_from_slice(&24u64.to_be_bytes()); // total box size: 16-byte header + 8 bytes body
+        buf.extend_from_slice(&[0u8; 8]);
+        let header = read_box_header(&mut Cursor::new(buf)).unwrap().unwrap();
+        assert_eq!(header.content_len(), 8);
+    }
+
+    #[test]
+    fn continue_on_error_skips_a_malformed_infe_entry() {
+        // Two infe entries: a well-formed, non-Exif one, followed by one
+        // that is truncated mid-field at true EOF (no bytes follow it at
+        // all, so reading its remaining fields fails with a real I/O
+        // error rather than merely misreading a sibling box's bytes).
+        let mut infe1_body = Vec::new();
+        infe1_body.extend_from_slice(&[2, 0, 0, 0]); // version 2
+        infe1_body.extend_from_slice(&1u16.to_be_bytes()); // item_id
+        infe1_body.extend_from_slice(&[0, 0]); // protection index
+        infe1_body.extend_from_slice(b"mime");
+        let infe1 = bmff_box(b"infe", &infe1_body);
+
+        let mut infe2 = Vec::new();
+        infe2.extend_from_slice(&12u32.to_be_bytes()); // header + version/flags only, no fields
+        infe2.extend_from_slice(b"infe");
+        infe2.extend_from_slice(&[3, 0, 0, 0]); // version 3, then nothing: true EOF
+
+        let mut iinf_body = Vec::new();
+        iinf_body.extend_from_slice(&[0, 0, 0, 0]); // version 0, flags 0
+        iinf_body.extend_from_slice(&2u16.to_be_bytes()); // entry_count
+        iinf_body.extend_from_slice(&infe1);
+        iinf_body.extend_from_slice(&infe2);
+        let iinf = bmff_box(b"iinf", &iinf_body);
+        let end = iinf.len() as u64;
+
+        let err = find_exif_item_id(&mut Cursor::new(iinf.clone()), 0, end, false).unwrap_err();
+        assert!(matches!(err, Error::InvalidFormat("Truncated infe box")));
+
+        let found = find_exif_item_id(&mut Cursor::new(iinf), 0, end, true).unwrap();
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn ftyp_brand_check_does_not_treat_minor_version_as_a_brand() {
+        // major="mp41" (rejected), minor_version bytes equal an accepted
+        // brand; this must still be rejected because minor_version is
+        // not a compatible-brands entry.
+        let mut body = Vec::new();
+        body.extend_from_slice(b"mp41");
+        body.extend_from_slice(b"heic");
+        let ftyp = bmff_box(b"ftyp", &body);
+        let header = BoxHeader {
+            boxtype: *b"ftyp", next_box_pos: ftyp.len() as u64, box_pos: 0, header_len: 8,
+        };
+        let mut cursor = Cursor::new(ftyp[8..].to_vec());
+        let err = check_ftyp_brands(&mut cursor, &header, 1 << 20).unwrap_err();
+        assert!(matches!(err, Error::InvalidFormat(_)));
+    }
+}
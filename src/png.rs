@@ -0,0 +1,149 @@
+//
+// Copyright (c) 2016 KAMADA Ken'ichi.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+// 1. Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in the
+//    documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED.  IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE
+// FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+// DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS
+// OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION)
+// HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT
+// LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY
+// OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF
+// SUCH DAMAGE.
+//
+
+//! Extraction of Exif data from the `eXIf` chunk of a PNG file.
+//!
+//! The chunk's data is already a complete TIFF/Exif stream, so it is
+//! handed to the TIFF parser unchanged.
+
+use std::io;
+
+use crate::alloc::try_alloc_vec_u8;
+use crate::error::{Error, ErrorContext};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// Reads Exif data from a PNG file and returns the `eXIf` chunk's
+/// contents, which is a TIFF-formatted Exif attribute block.
+///
+/// `max_alloc` bounds every size-driven allocation made while parsing;
+/// see `crate::alloc`.
+pub fn get_exif_attr<R>(reader: &mut R, max_alloc: usize) -> Result<Vec<u8>, Error>
+where R: io::Read {
+    let mut sig = [0u8; 8];
+    reader.read_exact(&mut sig)
+        .map_err(|_| Error::InvalidFormat("Truncated PNG signature"))?;
+    if sig != PNG_SIGNATURE {
+        return Err(Error::InvalidFormat("Not a PNG file"));
+    }
+
+    let mut pos = sig.len() as u64;
+    loop {
+        let mut chunk_hdr = [0u8; 8];
+        let mut filled = 0;
+        while filled < chunk_hdr.len() {
+            match reader.read(&mut chunk_hdr[filled..]) {
+                Ok(0) if filled == 0 => return Err(Error::NotFound("PNG")),
+                Ok(0) => return Err(Error::InvalidFormat("Truncated PNG chunk header")
+                    .with_context(ErrorContext::at(pos))),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let len = u32::from_be_bytes(chunk_hdr[0..4].try_into().unwrap());
+        let chunk_type = &chunk_hdr[4..8];
+        if chunk_type == b"eXIf" {
+            let mut data = try_alloc_vec_u8(len as usize, max_alloc, "PNG eXIf chunk")
+                .map_err(|e| e.with_context(ErrorContext::at(pos)))?;
+            reader.read_exact(&mut data)
+                .map_err(|_| Error::InvalidFormat("Truncated eXIf chunk").with_context(ErrorContext::at(pos)))?;
+            return Ok(data);
+        } else if chunk_type == b"IEND" {
+            return Err(Error::NotFound("PNG"));
+        } else {
+            skip(reader, len as u64 + 4)
+                .map_err(|_| Error::InvalidFormat("Truncated PNG chunk").with_context(ErrorContext::at(pos)))?;
+        }
+        pos += chunk_hdr.len() as u64 + len as u64 + 4 /* CRC already skipped above */;
+    }
+}
+
+/// Discards `n` bytes from `reader` without assuming it implements `Seek`.
+fn skip<R: io::Read>(reader: &mut R, mut n: u64) -> io::Result<()> {
+    let mut buf = [0u8; 4096];
+    while n > 0 {
+        let want = (n as usize).min(buf.len());
+        reader.read_exact(&mut buf[..want])?;
+        n -= want as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut c = Vec::new();
+        c.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        c.extend_from_slice(chunk_type);
+        c.extend_from_slice(data);
+        c.extend_from_slice(&[0u8; 4]); // CRC, not checked by this parser
+        c
+    }
+
+    fn minimal_png_with_exif(exif_tiff: &[u8]) -> Vec<u8> {
+        let mut file = PNG_SIGNATURE.to_vec();
+        file.extend_from_slice(&png_chunk(b"IHDR", &[0u8; 13]));
+        file.extend_from_slice(&png_chunk(b"eXIf", exif_tiff));
+        file.extend_from_slice(&png_chunk(b"IEND", &[]));
+        file
+    }
+
+    #[test]
+    fn extracts_exif_chunk() {
+        let tiff = b"MM\0*fake-tiff-body";
+        let file = minimal_png_with_exif(tiff);
+        let got = get_exif_attr(&mut Cursor::new(file), 1 << 20).unwrap();
+        assert_eq!(got, tiff);
+    }
+
+    #[test]
+    fn rejects_non_png_signature() {
+        let err = get_exif_attr(&mut Cursor::new(b"not a png".to_vec()), 1 << 20).unwrap_err();
+        assert!(matches!(err, Error::InvalidFormat(_)));
+    }
+
+    #[test]
+    fn missing_exif_chunk_is_not_found() {
+        let mut file = PNG_SIGNATURE.to_vec();
+        file.extend_from_slice(&png_chunk(b"IHDR", &[0u8; 13]));
+        file.extend_from_slice(&png_chunk(b"IEND", &[]));
+        let err = get_exif_attr(&mut Cursor::new(file), 1 << 20).unwrap_err();
+        assert!(matches!(err, Error::NotFound(_)));
+    }
+
+    #[test]
+    fn oversized_chunk_length_is_rejected_without_allocating() {
+        let mut file = PNG_SIGNATURE.to_vec();
+        file.extend_from_slice(&u32::MAX.to_be_bytes());
+        file.extend_from_slice(b"eXIf");
+        let err = get_exif_attr(&mut Cursor::new(file), 1024).unwrap_err();
+        assert!(matches!(err.into_source(), Error::AllocationLimit(_)));
+    }
+}
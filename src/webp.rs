@@ -0,0 +1,206 @@
+//
+// Copyright (c) 2016 KAMADA Ken'ichi.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+// 1. Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in the
+//    documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED.  IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE
+// FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+// DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS
+// OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION)
+// HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT
+// LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY
+// OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF
+// SUCH DAMAGE.
+//
+
+//! Extraction of Exif data from the `EXIF` chunk of a WebP (RIFF) file.
+
+use std::io;
+
+use crate::alloc::try_alloc_vec_u8;
+use crate::error::{Error, ErrorContext};
+
+// Bit in the VP8X "flags" byte that advertises the presence of an Exif
+// chunk (bit 3 counting from the least significant bit, just below the
+// alpha bit 0x10).
+const VP8X_HAS_EXIF: u8 = 0x08;
+
+/// Reads Exif data from a WebP file and returns the `EXIF` chunk's
+/// contents, with any leading `Exif\0\0` prefix stripped, as a
+/// TIFF-formatted Exif attribute block.
+///
+/// `max_alloc` bounds every size-driven allocation made while parsing;
+/// see `crate::alloc`.
+pub fn get_exif_attr<R>(reader: &mut R, max_alloc: usize) -> Result<Vec<u8>, Error>
+where R: io::Read {
+    let mut riff_hdr = [0u8; 12];
+    reader.read_exact(&mut riff_hdr)
+        .map_err(|_| Error::InvalidFormat("Truncated RIFF header"))?;
+    if &riff_hdr[0..4] != b"RIFF" || &riff_hdr[8..12] != b"WEBP" {
+        return Err(Error::InvalidFormat("Not a WebP file"));
+    }
+
+    let mut pos = riff_hdr.len() as u64;
+    let mut saw_vp8x_without_exif_flag = false;
+    loop {
+        let mut chunk_hdr = [0u8; 8];
+        let mut filled = 0;
+        while filled < chunk_hdr.len() {
+            match reader.read(&mut chunk_hdr[filled..]) {
+                Ok(0) if filled == 0 => return Err(Error::NotFound("WebP")),
+                Ok(0) => return Err(Error::InvalidFormat("Truncated RIFF chunk header")
+                    .with_context(ErrorContext::at(pos))),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let fourcc = &chunk_hdr[0..4];
+        let len = u32::from_le_bytes(chunk_hdr[4..8].try_into().unwrap());
+        let padded_len = len as u64 + (len & 1) as u64;
+
+        if fourcc == b"VP8X" {
+            if len == 0 {
+                return Err(Error::InvalidFormat("Truncated VP8X chunk").with_context(ErrorContext::at(pos)));
+            }
+            let mut flags = [0u8; 1];
+            reader.read_exact(&mut flags)
+                .map_err(|_| Error::InvalidFormat("Truncated VP8X chunk").with_context(ErrorContext::at(pos)))?;
+            if flags[0] & VP8X_HAS_EXIF == 0 {
+                saw_vp8x_without_exif_flag = true;
+            }
+            skip(reader, padded_len - 1)
+                .map_err(|_| Error::InvalidFormat("Truncated VP8X chunk").with_context(ErrorContext::at(pos)))?;
+        } else if fourcc == b"EXIF" {
+            let mut data = try_alloc_vec_u8(len as usize, max_alloc, "WebP EXIF chunk")
+                .map_err(|e| e.with_context(ErrorContext::at(pos)))?;
+            reader.read_exact(&mut data)
+                .map_err(|_| Error::InvalidFormat("Truncated EXIF chunk").with_context(ErrorContext::at(pos)))?;
+            if data.starts_with(b"Exif\0\0") {
+                data.drain(..6);
+            }
+            return Ok(data);
+        } else {
+            if saw_vp8x_without_exif_flag {
+                return Err(Error::NotFound("WebP"));
+            }
+            skip(reader, padded_len)
+                .map_err(|_| Error::InvalidFormat("Truncated RIFF chunk").with_context(ErrorContext::at(pos)))?;
+        }
+        pos += chunk_hdr.len() as u64 + padded_len;
+    }
+}
+
+/// Discards `n` bytes from `reader` without assuming it implements `Seek`.
+fn skip<R: io::Read>(reader: &mut R, mut n: u64) -> io::Result<()> {
+    let mut buf = [0u8; 4096];
+    while n > 0 {
+        let want = (n as usize).min(buf.len());
+        reader.read_exact(&mut buf[..want])?;
+        n -= want as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn riff_chunk(fourcc: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut c = Vec::new();
+        c.extend_from_slice(fourcc);
+        c.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        c.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            c.push(0);
+        }
+        c
+    }
+
+    fn riff_file(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = b"WEBP".to_vec();
+        for c in chunks {
+            body.extend_from_slice(c);
+        }
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        file.extend_from_slice(&body);
+        file
+    }
+
+    #[test]
+    fn extracts_exif_chunk_without_prefix() {
+        let tiff = b"MM\0*fake-tiff-body";
+        let file = riff_file(&[
+            riff_chunk(b"VP8 ", &[0u8; 4]),
+            riff_chunk(b"EXIF", tiff),
+        ]);
+        let got = get_exif_attr(&mut Cursor::new(file), 1 << 20).unwrap();
+        assert_eq!(got, tiff);
+    }
+
+    #[test]
+    fn strips_exif_prefix() {
+        let tiff = b"MM\0*fake-tiff-body";
+        let mut prefixed = b"Exif\0\0".to_vec();
+        prefixed.extend_from_slice(tiff);
+        let file = riff_file(&[riff_chunk(b"EXIF", &prefixed)]);
+        let got = get_exif_attr(&mut Cursor::new(file), 1 << 20).unwrap();
+        assert_eq!(got, tiff);
+    }
+
+    #[test]
+    fn rejects_non_riff_signature() {
+        let err = get_exif_attr(&mut Cursor::new(b"not a webp..".to_vec()), 1 << 20).unwrap_err();
+        assert!(matches!(err, Error::InvalidFormat(_)));
+    }
+
+    #[test]
+    fn vp8x_without_exif_flag_is_not_found() {
+        let mut vp8x_data = vec![0u8; 10];
+        vp8x_data[0] = 0; // flags byte: Exif bit not set
+        let file = riff_file(&[
+            riff_chunk(b"VP8X", &vp8x_data),
+            riff_chunk(b"ANIM", &[0u8; 4]),
+        ]);
+        let err = get_exif_attr(&mut Cursor::new(file), 1 << 20).unwrap_err();
+        assert!(matches!(err, Error::NotFound(_)));
+    }
+
+    #[test]
+    fn missing_exif_chunk_is_not_found() {
+        let file = riff_file(&[riff_chunk(b"VP8 ", &[0u8; 4])]);
+        let err = get_exif_attr(&mut Cursor::new(file), 1 << 20).unwrap_err();
+        assert!(matches!(err, Error::NotFound(_)));
+    }
+
+    #[test]
+    fn zero_length_vp8x_chunk_errors_instead_of_underflowing() {
+        let file = riff_file(&[riff_chunk(b"VP8X", &[])]);
+        let err = get_exif_attr(&mut Cursor::new(file), 1 << 20).unwrap_err();
+        assert!(matches!(err.into_source(), Error::InvalidFormat(_)));
+    }
+
+    #[test]
+    fn oversized_chunk_length_is_rejected_without_allocating() {
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(b"WEBP");
+        file.extend_from_slice(b"EXIF");
+        file.extend_from_slice(&u32::MAX.to_le_bytes());
+        let err = get_exif_attr(&mut Cursor::new(file), 1024).unwrap_err();
+        assert!(matches!(err.into_source(), Error::AllocationLimit(_)));
+    }
+}